@@ -4,30 +4,117 @@
 mod render;
 
 use std::error::Error;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::io::{stdout, Write, BufWriter};
 
 use image::{ImageBuffer, Rgba};
 
+use cgmath::{Matrix3, Matrix4, Rad, Vector3};
+
 use crossterm::{ExecutableCommand, QueueableCommand, terminal::{Clear, ClearType},
     style::{self, SetAttribute, Color, Attribute},
     cursor, terminal
 };
 
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+
+use render::InstanceData;
+
 //use ansi_term::Colour::RGB;
 
-fn get_ascii(pixel: Rgba<u8>) -> char {
-    if pixel[3] == 0 {
-        ' '
-    }
-    else {
-        '0'
+/// Map a shaded pixel onto a character in `ramp`, ordered darkest to
+/// brightest, by its alpha-scaled luminance. Falls back to a space if
+/// `ramp` is empty.
+fn get_ascii(pixel: Rgba<u8>, ramp: &[char], invert: bool) -> char {
+    if ramp.is_empty() {
+        return ' ';
     }
+
+    let alpha = pixel[3] as f32 / 255.0;
+    let luminance =
+        (0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32) / 255.0 * alpha;
+    let luminance = if invert { 1.0 - luminance } else { luminance };
+
+    let index = (luminance * (ramp.len() - 1) as f32).round() as usize;
+    ramp[index.min(ramp.len() - 1)]
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn load_skybox_face(path: &str) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Box<dyn Error>> {
+    Ok(image::open(path)?.to_rgba())
+}
+
+/// Load the six cubemap face images that give the ASCII scene a background
+/// instead of transparent black.
+fn load_skybox() -> Result<render::SkyboxFaces, Box<dyn Error>> {
+    Ok(render::SkyboxFaces {
+        left: load_skybox_face("assets/skybox/left.png")?,
+        right: load_skybox_face("assets/skybox/right.png")?,
+        bottom: load_skybox_face("assets/skybox/bottom.png")?,
+        top: load_skybox_face("assets/skybox/top.png")?,
+        back: load_skybox_face("assets/skybox/back.png")?,
+        front: load_skybox_face("assets/skybox/front.png")?
+    })
+}
+
+const INSTANCE_OFFSETS: [f32; 3] = [-1.5, 0.0, 1.5];
+const INSTANCE_COLOURS: [[f32; 3]; 3] = [[0.8, 0.3, 0.3], [0.3, 0.8, 0.3], [0.3, 0.3, 0.8]];
+
+/// A small forest of Suzannes, one per `INSTANCE_OFFSETS` entry, each
+/// turning at its own rate - this is the per-frame update that replaces
+/// the old single global rotation baked into `Pipeline::render`.
+fn build_instances(elapsed: Duration) -> Vec<InstanceData> {
+    let seconds = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1_000_000_000.0;
+
+    INSTANCE_OFFSETS
+        .iter()
+        .zip(INSTANCE_COLOURS.iter())
+        .enumerate()
+        .map(|(i, (&x_offset, &colour))| {
+            let spin = Rad(seconds * (0.5 + i as f32 * 0.25));
+            let model =
+                Matrix4::from_translation(Vector3::new(x_offset, 0.0, 0.0))
+                    * Matrix4::from(Matrix3::from_angle_y(spin));
+
+            InstanceData { modelmatrix: model.into(), colour }
+        })
+        .collect()
+}
+
+/// Suzanne's raw geometry, kept around so both the headless and windowed
+/// entry points can hand it to `set_instanced_mesh`.
+struct Suzanne {
+    vertices: Vec<(f32, f32, f32)>,
+    indices: Vec<u32>
+}
+
+fn load_suzanne() -> Result<Suzanne, Box<dyn Error>> {
+    let (models, _materials) = tobj::load_obj("suzanne.obj", false)?;
+    let mesh = &models[0].mesh;
+
+    Ok(Suzanne {
+        vertices: mesh.positions.chunks(3).map(|p| (p[0], p[1], p[2])).collect(),
+        indices: mesh.indices.clone()
+    })
+}
+
+/// Upload the skybox and the instanced forest of Suzannes onto a freshly
+/// built `pipeline` - shared by the headless and windowed entry points.
+fn wire_scene(pipeline: &mut render::Pipeline, suzanne: &Suzanne) -> Result<(), Box<dyn Error>> {
+    pipeline.set_skybox(load_skybox()?)?;
+    pipeline.set_instanced_mesh(
+        suzanne.vertices.clone(),
+        suzanne.indices.clone(),
+        build_instances(Duration::ZERO)
+    )?;
+
+    Ok(())
+}
+
+/// Render off-screen and print the result as colored ASCII in the terminal.
+fn run_headless() -> Result<(), Box<dyn Error>> {
     let mut stdout = stdout();
-    
+
     stdout.execute(Clear(ClearType::All))?;
     stdout.execute(cursor::Hide)?;
 
@@ -36,14 +123,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     let width = cols as u32;
     let height = rows as u32;
 
-    let (models, _materials) = tobj::load_obj("suzanne.obj", false)?;
-    let mesh = &models[0].mesh;
-    let vertices = mesh.positions.iter().cloned();
-    let normals = mesh.normals.iter().cloned();
-    let indices = mesh.indices.iter().cloned();
-
-    let renderer = render::Renderer::new()?;
-    let mut pipeline = render::Pipeline::new(renderer, width, height, vertices, normals, indices)?;
+    let suzanne = load_suzanne()?;
+    let renderer = render::Renderer::new_headless()?;
+    let mut pipeline = render::Pipeline::new(renderer, width, height, vec![])?;
+    wire_scene(&mut pipeline, &suzanne)?;
 
     let blank_image = vec![0 as u8; (height * width * 4) as usize];
     let blank_buffer = ImageBuffer::<Rgba<u8>, Vec<u8>>::
@@ -62,7 +145,8 @@ fn main() -> Result<(), Box<dyn Error>> {
         let frame_duration = Instant::now();
         let mut stdout_lock = stdout.lock();
         i = 1 - i;
-        let new_image = pipeline.render(rotation_start.elapsed())?.clone();
+        pipeline.set_instances(build_instances(rotation_start.elapsed()))?;
+        let new_image = pipeline.render()?.clone();
         swapchain[i] = ImageBuffer::<Rgba<u8>, Vec<u8>>::
         from_raw(
             width, 
@@ -70,12 +154,13 @@ fn main() -> Result<(), Box<dyn Error>> {
             new_image
         ).unwrap();
 
+        let ascii_ramp: Vec<char> = pipeline.ascii_ramp.chars().collect();
         for (x, y, pixel) in swapchain[i].enumerate_pixels() {
             if *swapchain[1 - i].get_pixel(x, y) != *pixel {
                 stdout_lock
                     .queue(cursor::MoveTo(x as u16, y as u16))?
                     .queue(style::SetForegroundColor(Color::Rgb { r: pixel[0], g: pixel[1], b: pixel[2] }))?
-                    .queue(style::Print(get_ascii(*pixel)))?;
+                    .queue(style::Print(get_ascii(*pixel, &ascii_ramp, pipeline.ascii_invert)))?;
             }
         }
         frame_average = frame_average * 0.95 + frame_duration.elapsed().as_millis() as f32 * 0.05;
@@ -84,35 +169,43 @@ fn main() -> Result<(), Box<dyn Error>> {
             .queue(style::SetForegroundColor(Color::Rgb { r: 255, g: 0, b: 0 }))?
             .queue(style::Print(1.0 / (frame_average / 1000.0)))?;
     }
-       
-/*
-    loop {
-        let frame_duration = Instant::now();
-        let mut output_text = Vec::<u8>::new();
-        //let mut stdout_lock = stdout.lock();
-        let new_image = pipeline.render(rotation_start.elapsed())?.clone();
-        swapchain[i] = ImageBuffer::<Rgba<u8>, Vec<u8>>::
-        from_raw(
-            width, 
-            height, 
-            new_image
-        ).unwrap();
-        for (x, y, pixel) in swapchain[i].enumerate_pixels() {
-            output_text
-                .queue(cursor::MoveTo(x as u16, y as u16))?
-                .queue(style::SetForegroundColor(Color::Rgb { r: pixel[0], g: pixel[1], b: pixel[2] }))?
-                .queue(style::Print(get_ascii(*pixel)))?;
+}
+
+/// Open a real window and drive the GPU swapchain presentation path
+/// directly instead of reading pixels back for terminal ASCII output.
+fn run_windowed() -> Result<(), Box<dyn Error>> {
+    let event_loop = EventLoop::new();
+    let (renderer, mut window_target) = render::Renderer::new_windowed(&event_loop)?;
+
+    let dimensions: [u32; 2] = window_target.window().inner_size().into();
+    let suzanne = load_suzanne()?;
+    let mut pipeline = render::Pipeline::new(renderer, dimensions[0], dimensions[1], vec![])?;
+    wire_scene(&mut pipeline, &suzanne)?;
+
+    let rotation_start = Instant::now();
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
+
+        match event {
+            Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                *control_flow = ControlFlow::Exit;
+            },
+            Event::MainEventsCleared => {
+                pipeline.set_instances(build_instances(rotation_start.elapsed())).unwrap();
+                pipeline.present(&mut window_target).unwrap();
+            },
+            _ => ()
         }
-        frame_average = frame_average * 0.9 + frame_duration.elapsed().as_millis() as f32 * 0.1;
-        output_text
-            .queue(cursor::MoveTo(0, 0))?
-            .queue(style::SetForegroundColor(Color::Rgb { r: 255, g: 0, b: 0 }))?
-            .queue(style::Print(1.0 / (frame_average / 1000.0)))?;
-        std::io::copy(&mut &output_text[..], &mut stdout)?;
-        //stdout_lock.write_all(&output_text)?;
+    });
+}
+
+/// Run windowed GPU presentation when invoked with `--windowed`, otherwise
+/// fall back to the original headless terminal ASCII output.
+fn main() -> Result<(), Box<dyn Error>> {
+    if std::env::args().any(|arg| arg == "--windowed") {
+        run_windowed()
+    } else {
+        run_headless()
     }
-    
-    stdout.queue(SetAttribute(Attribute::Reset))?;
-    Ok(())
-    */
 }
@@ -0,0 +1,142 @@
+use std::error::Error;
+use std::iter;
+use std::sync::Arc;
+
+use cgmath::Matrix4;
+
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, CpuBufferPool};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Device;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::pipeline::vertex::OneVertexOneInstanceDefinition;
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+
+use super::Vertex;
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/render/instance_vert.glsl"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/render/instance_frag.glsl"
+    }
+}
+
+/// Per-instance data for hardware-instanced draws: each instance gets its
+/// own model matrix and flat colour, bound at a per-instance input rate.
+#[derive(Default, Copy, Clone)]
+pub struct InstanceData {
+    pub modelmatrix: [[f32; 4]; 4],
+    pub colour: [f32; 3]
+}
+vulkano::impl_vertex!(InstanceData, modelmatrix, colour);
+
+/// One base mesh drawn many times cheaply via hardware instancing: the
+/// vertex/index buffers are uploaded once, while per-instance transforms and
+/// colours live in a separate buffer the caller can replace every frame
+/// (e.g. recomputed from `elapsed`) so instances move independently.
+pub struct InstancedMesh {
+    pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    index_buffer: Arc<CpuAccessibleBuffer<[u32]>>,
+    instance_buffer: Arc<CpuAccessibleBuffer<[InstanceData]>>,
+    uniform_buffer: CpuBufferPool<vs::ty::Data>
+}
+
+impl InstancedMesh {
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+        viewport: Viewport,
+        vertices: Vec<(f32, f32, f32)>,
+        indices: Vec<u32>,
+        instances: Vec<InstanceData>
+    ) -> Result<Self, Box<dyn Error>> {
+        let vs = vs::Shader::load(device.clone())?;
+        let fs = fs::Shader::load(device.clone())?;
+
+        let pipeline = Arc::new(
+            GraphicsPipeline::start()
+                .vertex_input(OneVertexOneInstanceDefinition::<Vertex, InstanceData>::new())
+                .vertex_shader(vs.main_entry_point(), ())
+                .triangle_list()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .viewports(iter::once(viewport))
+                .fragment_shader(fs.main_entry_point(), ())
+                .depth_stencil_simple_depth()
+                .render_pass(Subpass::from(render_pass, 0).unwrap())
+                .build(device.clone())?
+        ) as Arc<dyn GraphicsPipelineAbstract + Send + Sync>;
+
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::all(),
+            false,
+            vertices.into_iter().map(|position| Vertex { position })
+        )?;
+
+        let index_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(), BufferUsage::all(), false, indices.into_iter()
+        )?;
+
+        let instance_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(), BufferUsage::all(), false, instances.into_iter()
+        )?;
+
+        let uniform_buffer = CpuBufferPool::<vs::ty::Data>::new(device, BufferUsage::all());
+
+        Ok(Self { pipeline, vertex_buffer, index_buffer, instance_buffer, uniform_buffer })
+    }
+
+    /// Replace the per-instance transforms/colours, e.g. once per frame with
+    /// matrices recomputed from elapsed time.
+    pub fn set_instances(&mut self, device: &Arc<Device>, instances: Vec<InstanceData>) -> Result<(), Box<dyn Error>> {
+        self.instance_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(), BufferUsage::all(), false, instances.into_iter()
+        )?;
+
+        Ok(())
+    }
+
+    pub fn record(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        view: Matrix4<f32>,
+        proj: Matrix4<f32>
+    ) -> Result<(), Box<dyn Error>> {
+        let uniform_data = vs::ty::Data {
+            view: view.into(),
+            proj: proj.into()
+        };
+        let uniform_subbuffer = self.uniform_buffer.next(uniform_data).unwrap();
+
+        let layout = self.pipeline.descriptor_set_layout(0).unwrap();
+        let descriptor_set = Arc::new(
+            PersistentDescriptorSet::start(layout.clone())
+                .add_buffer(uniform_subbuffer)
+                .unwrap()
+                .build()
+                .unwrap()
+        );
+
+        builder
+            .draw_indexed(
+                self.pipeline.clone(),
+                &DynamicState::none(),
+                (self.vertex_buffer.clone(), self.instance_buffer.clone()),
+                self.index_buffer.clone(),
+                descriptor_set,
+                ()
+            )
+            .unwrap();
+
+        Ok(())
+    }
+}
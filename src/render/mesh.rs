@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use cgmath::Matrix4;
+
+/// A single drawable object: its own geometry plus a model-space transform.
+///
+/// `Pipeline` uploads each mesh's vertex/normal/index data to its own GPU
+/// buffers and multiplies `transform` into the per-draw uniform, so a scene
+/// can hold any number of independently positioned objects. The geometry is
+/// kept behind `Arc` so it can be handed to a background upload worker
+/// without copying.
+#[derive(Clone)]
+pub struct Mesh {
+    pub vertices: Arc<Vec<f32>>,
+    pub normals: Arc<Vec<f32>>,
+    pub indices: Arc<Vec<u32>>,
+    pub transform: Matrix4<f32>,
+}
+
+impl Mesh {
+    pub fn new(
+        vertices: impl IntoIterator<Item = f32>,
+        normals: impl IntoIterator<Item = f32>,
+        indices: impl IntoIterator<Item = u32>,
+        transform: Matrix4<f32>,
+    ) -> Self {
+        Self {
+            vertices: Arc::new(vertices.into_iter().collect()),
+            normals: Arc::new(normals.into_iter().collect()),
+            indices: Arc::new(indices.into_iter().collect()),
+            transform,
+        }
+    }
+}
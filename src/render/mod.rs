@@ -1,7 +1,7 @@
 use std::error::Error;
 use std::sync::Arc;
 use std::iter;
-use std::time::Duration;
+use std::thread;
 
 use vulkano::instance::{Instance, InstanceExtensions, PhysicalDevice};
 use vulkano::device::{Device, DeviceExtensions, Queue};
@@ -14,7 +14,25 @@ use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState, SubpassCon
 use vulkano::image::{Dimensions, StorageImage, AttachmentImage};
 use vulkano::sync::{self, GpuFuture};
 
-use cgmath::{Matrix3, Matrix4, Point3, Rad, Vector3};
+use vulkano_win::VkSurfaceBuild;
+use winit::event_loop::EventLoop;
+use winit::window::WindowBuilder;
+
+use cgmath::{Matrix4, Point3, Rad, Vector3};
+
+mod mesh;
+pub use mesh::Mesh;
+
+mod skybox;
+pub use skybox::SkyboxFaces;
+
+mod instancing;
+pub use instancing::InstanceData;
+
+mod present;
+pub use present::WindowTarget;
+
+mod worker;
 
 #[derive(Default, Copy, Clone)]
 pub struct Vertex {
@@ -46,10 +64,13 @@ pub struct Renderer {
     logical_device: Arc<Device>,
     queue: Arc<Queue>,
     uniform_buffer: CpuBufferPool::<vs::ty::Data>,
+    material_buffer: CpuBufferPool::<fs::ty::Material>,
+    light_buffer: CpuBufferPool::<fs::ty::Light>,
     vs: vs::Shader,
     fs: fs::Shader,
 
-    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    buffer_workers: worker::BufferWorkers
 }
 
 pub struct Pipeline {
@@ -58,76 +79,163 @@ pub struct Pipeline {
     pub width: u32,
     pub height: u32,
 
+    pub material: fs::ty::Material,
+    pub light: fs::ty::Light,
+
+    /// Character ramp `get_ascii` indexes into, ordered darkest to
+    /// brightest (e.g. `" .:-=+*#%@"`). Tune this for the target terminal
+    /// font's perceived density.
+    pub ascii_ramp: String,
+    /// Flip the ramp so low luminance maps to the brightest character
+    /// instead of the darkest, e.g. for a light-on-dark vs dark-on-light
+    /// terminal theme.
+    pub ascii_invert: bool,
+
     clear_values: Vec<vulkano::format::ClearValue>,
     image: Arc<StorageImage<Format>>,
+    viewport: Viewport,
 
     pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
     framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
 
+    meshes: Vec<MeshBuffers>,
+    skybox: Option<skybox::Skybox>,
+    instanced_mesh: Option<instancing::InstancedMesh>,
+
+    output_buffer: Arc<CpuAccessibleBuffer<[u8]>>
+}
+
+/// GPU-resident copy of a `Mesh`: its buffers plus the transform to push
+/// into the uniform when it's drawn.
+struct MeshBuffers {
     vertex_buffer: Arc<CpuAccessibleBuffer<[f32]>>,
     normal_buffer: Arc<CpuAccessibleBuffer<[f32]>>,
     index_buffer: Arc<CpuAccessibleBuffer<[u32]>>,
+    transform: Matrix4<f32>
+}
 
-    output_buffer: Arc<CpuAccessibleBuffer<[u8]>>
+impl From<worker::MeshUpload> for MeshBuffers {
+    fn from(upload: worker::MeshUpload) -> Self {
+        Self {
+            vertex_buffer: upload.vertex_buffer,
+            normal_buffer: upload.normal_buffer,
+            index_buffer: upload.index_buffer,
+            transform: upload.transform
+        }
+    }
+}
+
+fn upload_meshes(workers: &worker::BufferWorkers, meshes: &[Mesh]) -> Result<Vec<MeshBuffers>, Box<dyn Error>> {
+    let jobs = meshes
+        .iter()
+        .map(|mesh| worker::MeshJob {
+            vertices: mesh.vertices.clone(),
+            normals: mesh.normals.clone(),
+            indices: mesh.indices.clone(),
+            transform: mesh.transform
+        })
+        .collect();
+
+    Ok(workers.upload_all(jobs)?.into_iter().map(MeshBuffers::from).collect())
 }
 
 impl Pipeline {
-    pub fn render(&mut self, elapsed: Duration) -> Result<Vec<u8>, Box<dyn Error>> {
-        let uniform_buffer_subbuffer = {
-            let rotation = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1_000_000_000.0;
-            let rotation = Matrix3::from_angle_y(Rad(rotation as f32));
-            let aspect_ratio = (self.width as f32 / 2.0) / self.height as f32;
-            let projection = 
-                cgmath::perspective(
-                    Rad(std::f32::consts::FRAC_PI_2 / 1.5), 
-                    aspect_ratio, 
-                    0.01, 
-                    100.0
-                );
+    /// Record the scene (skybox, meshes, instanced mesh) into `builder`
+    /// against `framebuffer`. Shared by the headless and windowed
+    /// presentation paths so they stay in lockstep.
+    fn record_draws(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>
+    ) -> Result<(), Box<dyn Error>> {
+        let aspect_ratio = (self.width as f32 / 2.0) / self.height as f32;
+        let projection =
+            cgmath::perspective(
+                Rad(std::f32::consts::FRAC_PI_2 / 1.5),
+                aspect_ratio,
+                0.01,
+                100.0
+            );
 
-            let view = 
-                Matrix4::look_at(
-                    Point3::new(-0.5, 1.0, -2.0),
-                    Point3::new(0.0, 0.0, 0.0),
-                    Vector3::new(0.0, -1.0, 0.0),
-                );
-    
-            let scale = Matrix4::from_scale(1.0);
-            let uniform_data = vs::ty::Data {
-                world: Matrix4::from(rotation).into(),
-                view: (view * scale).into(),
-                proj: projection.into()
-            };
-            self.renderer.uniform_buffer.next(uniform_data).unwrap()
-        };
+        let view =
+            Matrix4::look_at(
+                Point3::new(-0.5, 1.0, -2.0),
+                Point3::new(0.0, 0.0, 0.0),
+                Vector3::new(0.0, -1.0, 0.0),
+            );
 
+        let scale = Matrix4::from_scale(1.0);
         let layout = self.pipeline.descriptor_set_layout(0).unwrap();
-        let descriptor_set = Arc::new(
-            PersistentDescriptorSet::start(layout.clone())
-                .add_buffer(uniform_buffer_subbuffer)
+        let lighting_layout = self.pipeline.descriptor_set_layout(1).unwrap();
+
+        let material_subbuffer = self.renderer.material_buffer.next(self.material).unwrap();
+        let light_subbuffer = self.renderer.light_buffer.next(self.light).unwrap();
+        let lighting_set = Arc::new(
+            PersistentDescriptorSet::start(lighting_layout.clone())
+                .add_buffer(material_subbuffer)
+                .unwrap()
+                .add_buffer(light_subbuffer)
                 .unwrap()
                 .build()
                 .unwrap()
             );
-        
-            let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(
-                self.renderer.logical_device.clone(),
-                self.renderer.queue.family()
-            )?;
+
+        builder.begin_render_pass(framebuffer, SubpassContents::Inline, self.clear_values.clone())?;
+
+        if let Some(skybox) = &self.skybox {
+            skybox.record(builder, view * scale, projection)?;
+        }
+
+        for mesh in &self.meshes {
+            let uniform_data = vs::ty::Data {
+                world: mesh.transform.into(),
+                view: (view * scale).into(),
+                proj: projection.into()
+            };
+            let uniform_buffer_subbuffer = self.renderer.uniform_buffer.next(uniform_data).unwrap();
+
+            let descriptor_set = Arc::new(
+                PersistentDescriptorSet::start(layout.clone())
+                    .add_buffer(uniform_buffer_subbuffer)
+                    .unwrap()
+                    .build()
+                    .unwrap()
+                );
+
+            builder
+                .draw_indexed(
+                    self.pipeline.clone(),
+                    &DynamicState::none(),
+                    vec![mesh.vertex_buffer.clone(), mesh.normal_buffer.clone()],
+                    mesh.index_buffer.clone(),
+                    (descriptor_set.clone(), lighting_set.clone()),
+                    ()
+                )
+                .unwrap();
+        }
+
+        if let Some(instanced_mesh) = &self.instanced_mesh {
+            instanced_mesh.record(builder, view * scale, projection)?;
+        }
+
+        builder.end_render_pass().unwrap();
+
+        Ok(())
+    }
+
+    /// Render a frame off-screen and read it back to the CPU, for terminal
+    /// ASCII output. Any per-frame motion comes from the caller updating
+    /// mesh transforms or instance data beforehand, e.g. via
+    /// `set_instances`.
+    pub fn render(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(
+            self.renderer.logical_device.clone(),
+            self.renderer.queue.family()
+        )?;
+
+        self.record_draws(&mut builder, self.framebuffer.clone())?;
 
         builder
-            .begin_render_pass(self.framebuffer.clone(), SubpassContents::Inline, self.clear_values.clone())?
-            .draw_indexed(
-                self.pipeline.clone(),
-                &DynamicState::none(),
-                vec![self.vertex_buffer.clone(), self.normal_buffer.clone()],
-                self.index_buffer.clone(),
-                descriptor_set.clone(),
-                ()
-            )
-            .unwrap()
-            .end_render_pass()
-            .unwrap()
             .copy_image_to_buffer(self.image.clone(), self.output_buffer.clone())
             .unwrap();
 
@@ -140,17 +248,26 @@ impl Pipeline {
         future.wait(None).unwrap();
 
         let v: Vec<u8> = (&self.output_buffer.read()?).to_vec();
-        
+
         Ok(v)
     }
 
+    /// Render a frame directly into the next swapchain image and present it
+    /// - the windowed counterpart to `render`'s CPU readback.
+    pub fn present(&mut self, window_target: &mut WindowTarget) -> Result<(), Box<dyn Error>> {
+        let device = self.renderer.logical_device.clone();
+        let queue = self.renderer.queue.clone();
+
+        window_target.present(device, queue, |builder, framebuffer| {
+            self.record_draws(builder, framebuffer)
+        })
+    }
+
     pub fn new(
-        renderer: Renderer, 
-        width: u32, 
-        height: u32, 
-        vertices: std::iter::Cloned<std::slice::Iter<f32>>, 
-        normals: std::iter::Cloned<std::slice::Iter<f32>>, 
-        indices: std::iter::Cloned<std::slice::Iter<u32>>
+        renderer: Renderer,
+        width: u32,
+        height: u32,
+        meshes: Vec<Mesh>
     ) -> Result<Self, Box<dyn Error>> {
 
         let dimensions = Dimensions::Dim2d {
@@ -212,21 +329,9 @@ impl Pipeline {
                 .unwrap()
         ) as Arc<dyn FramebufferAbstract + Send + Sync>;
 
-        let vertex_buffer =
-            CpuAccessibleBuffer::from_iter(renderer.logical_device.clone(), BufferUsage::all(), false, vertices)
-            .unwrap();
-
-        let normal_buffer = 
-            CpuAccessibleBuffer::from_iter(renderer.logical_device.clone(), BufferUsage::all(), false, normals)
-            .unwrap();
-
-        let index_buffer = 
-            CpuAccessibleBuffer::from_iter(renderer.logical_device.clone(), BufferUsage::all(), false, indices)
-            .unwrap();
-
-        
+        let meshes = upload_meshes(&renderer.buffer_workers, &meshes)?;
 
-        let output_buffer = 
+        let output_buffer =
             CpuAccessibleBuffer::from_iter(
                 renderer.logical_device.clone(),
                 BufferUsage::all(),
@@ -234,26 +339,98 @@ impl Pipeline {
                 (0 .. width * height * 4).map(|_| 0u8)
             )?;
 
+        let material = fs::ty::Material {
+            kd: [0.6, 0.6, 0.6],
+            shininess: 32.0,
+            ks: [0.3, 0.3, 0.3],
+            pad: 0.0,
+            ka: [0.1, 0.1, 0.1]
+        };
+
+        let light = fs::ty::Light {
+            position: [2.0, 2.0, 2.0, 1.0],
+            intensity: [1.0, 1.0, 1.0]
+        };
+
         Ok(
             Self {
                 renderer,
                 width,
                 height,
+                material,
+                light,
+                ascii_ramp: " .:-=+*#%@".to_string(),
+                ascii_invert: false,
                 clear_values,
                 image,
+                viewport,
                 pipeline,
                 framebuffer,
-                vertex_buffer,
-                normal_buffer,
-                index_buffer,
+                meshes,
+                skybox: None,
+                instanced_mesh: None,
                 output_buffer
             }
         )
     }
+
+    /// Replace the scene's meshes, uploading fresh GPU buffers for each one
+    /// across the renderer's background worker pool.
+    pub fn set_render_data(&mut self, meshes: Vec<Mesh>) -> Result<(), Box<dyn Error>> {
+        self.meshes = upload_meshes(&self.renderer.buffer_workers, &meshes)?;
+
+        Ok(())
+    }
+
+    /// Upload the cubemap background and enable it for subsequent frames.
+    pub fn set_skybox(&mut self, faces: SkyboxFaces) -> Result<(), Box<dyn Error>> {
+        self.skybox = Some(skybox::Skybox::new(
+            self.renderer.logical_device.clone(),
+            self.renderer.queue.clone(),
+            self.renderer.render_pass.clone(),
+            self.viewport.clone(),
+            faces
+        )?);
+
+        Ok(())
+    }
+
+    /// Enable hardware-instanced drawing of `vertices`/`indices`, one draw
+    /// call covering every entry in `instances`.
+    pub fn set_instanced_mesh(
+        &mut self,
+        vertices: Vec<(f32, f32, f32)>,
+        indices: Vec<u32>,
+        instances: Vec<InstanceData>
+    ) -> Result<(), Box<dyn Error>> {
+        self.instanced_mesh = Some(instancing::InstancedMesh::new(
+            self.renderer.logical_device.clone(),
+            self.renderer.render_pass.clone(),
+            self.viewport.clone(),
+            vertices,
+            indices,
+            instances
+        )?);
+
+        Ok(())
+    }
+
+    /// Replace the per-instance transforms/colours of the instanced mesh,
+    /// e.g. once per frame with matrices recomputed from elapsed time.
+    pub fn set_instances(&mut self, instances: Vec<InstanceData>) -> Result<(), Box<dyn Error>> {
+        if let Some(instanced_mesh) = &mut self.instanced_mesh {
+            instanced_mesh.set_instances(&self.renderer.logical_device, instances)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl Renderer {
-    pub fn new() -> Result<Self, Box<dyn Error>> {
+    /// Build a renderer against a headless device - no window/surface, just
+    /// enough to render into an off-screen image and read it back to the
+    /// CPU for terminal ASCII output.
+    pub fn new_headless() -> Result<Self, Box<dyn Error>> {
         let instance = Instance::new(None, &InstanceExtensions::none(), None)?;
         let physical_device = PhysicalDevice::enumerate(&instance).next().unwrap();
 
@@ -270,7 +447,47 @@ impl Renderer {
         )?;
         let queue = queues.next().unwrap();
 
+        Self::from_device(logical_device, queue)
+    }
+
+    /// Open a real window via `vulkano_win` and build a renderer whose
+    /// device additionally supports presenting a swapchain to it, for GPU
+    /// window output instead of terminal ASCII.
+    pub fn new_windowed(event_loop: &EventLoop<()>) -> Result<(Self, WindowTarget), Box<dyn Error>> {
+        let required_extensions = vulkano_win::required_extensions();
+        let instance = Instance::new(None, &required_extensions, None)?;
+        let physical_device = PhysicalDevice::enumerate(&instance).next().unwrap();
+
+        let surface = WindowBuilder::new().build_vk_surface(event_loop, instance.clone())?;
+
+        let queue_family = physical_device
+            .queue_families()
+            .find(|queue| queue.supports_graphics() && surface.is_supported(*queue).unwrap_or(false))
+            .unwrap();
+
+        let device_extensions = DeviceExtensions {
+            khr_swapchain: true,
+            ..DeviceExtensions::none()
+        };
+
+        let (logical_device, mut queues) = Device::new(
+            physical_device,
+            physical_device.supported_features(),
+            &device_extensions,
+            [(queue_family, 1.0)].iter().cloned()
+        )?;
+        let queue = queues.next().unwrap();
+
+        let renderer = Self::from_device(logical_device.clone(), queue.clone())?;
+        let window_target = WindowTarget::new(logical_device, physical_device, &queue, renderer.render_pass.clone(), surface)?;
+
+        Ok((renderer, window_target))
+    }
+
+    fn from_device(logical_device: Arc<Device>, queue: Arc<Queue>) -> Result<Self, Box<dyn Error>> {
         let uniform_buffer = CpuBufferPool::<vs::ty::Data>::new(logical_device.clone(), BufferUsage::all());
+        let material_buffer = CpuBufferPool::<fs::ty::Material>::new(logical_device.clone(), BufferUsage::all());
+        let light_buffer = CpuBufferPool::<fs::ty::Light>::new(logical_device.clone(), BufferUsage::all());
 
         let vs = vs::Shader::load(logical_device.clone())?;
         let fs = fs::Shader::load(logical_device.clone())?;
@@ -306,16 +523,21 @@ impl Renderer {
             )?
         );
 
+        let worker_count = thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+        let buffer_workers = worker::BufferWorkers::new(logical_device.clone(), worker_count);
+
         Ok(
             Self {
                 logical_device,
                 queue,
                 uniform_buffer,
+                material_buffer,
+                light_buffer,
                 vs,
                 fs,
-                render_pass
+                render_pass,
+                buffer_workers
             }
         )
-
     }
 }
\ No newline at end of file
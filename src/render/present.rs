@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract};
+use vulkano::image::{AttachmentImage, SwapchainImage};
+use vulkano::instance::PhysicalDevice;
+use vulkano::swapchain::{
+    self, AcquireError, ColorSpace, FullscreenExclusive, PresentMode, Surface, SurfaceTransform,
+    Swapchain, SwapchainCreationError
+};
+use vulkano::sync::{self, FlushError, GpuFuture};
+
+use winit::window::Window;
+
+/// A real GPU window the renderer can present directly to, as an alternative
+/// to the headless copy-to-buffer path used for terminal ASCII output.
+pub struct WindowTarget {
+    surface: Arc<Surface<Window>>,
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    swapchain: Arc<Swapchain<Window>>,
+    framebuffers: Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
+    previous_frame_end: Option<Box<dyn GpuFuture>>,
+    needs_recreate: bool
+}
+
+/// Build one multisampled intermediary/depth/resolve framebuffer per
+/// swapchain image, in the render pass's declared attachment order.
+fn build_framebuffers(
+    device: Arc<Device>,
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    dimensions: [u32; 2],
+    images: &[Arc<SwapchainImage<Window>>]
+) -> Result<Vec<Arc<dyn FramebufferAbstract + Send + Sync>>, Box<dyn Error>> {
+    images
+        .iter()
+        .map(|image| {
+            let intermediary = AttachmentImage::transient_multisampled(
+                device.clone(),
+                dimensions,
+                4,
+                Format::R8G8B8A8Unorm
+            )?;
+            let depth_buffer = AttachmentImage::transient_multisampled(
+                device.clone(),
+                dimensions,
+                4,
+                Format::D16Unorm
+            )?;
+
+            Ok(Arc::new(
+                Framebuffer::start(render_pass.clone())
+                    .add(intermediary)?
+                    .add(depth_buffer)?
+                    .add(image.clone())?
+                    .build()?
+            ) as Arc<dyn FramebufferAbstract + Send + Sync>)
+        })
+        .collect()
+}
+
+impl WindowTarget {
+    /// Build a swapchain and its per-image framebuffers over an
+    /// already-created `surface`. `render_pass` must be the same one the
+    /// scene's pipelines were built against.
+    pub fn new(
+        device: Arc<Device>,
+        physical_device: PhysicalDevice,
+        queue: &Arc<Queue>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+        surface: Arc<Surface<Window>>
+    ) -> Result<Self, Box<dyn Error>> {
+        let capabilities = surface.capabilities(physical_device)?;
+        let format = capabilities
+            .supported_formats
+            .iter()
+            .find(|(format, _)| *format == Format::R8G8B8A8Unorm)
+            .map(|(format, _)| *format)
+            .unwrap_or(capabilities.supported_formats[0].0);
+        let dimensions: [u32; 2] = surface.window().inner_size().into();
+        let alpha = capabilities.supported_composite_alpha.iter().next().unwrap();
+
+        let (swapchain, images) = Swapchain::new(
+            device.clone(),
+            surface.clone(),
+            capabilities.min_image_count,
+            format,
+            dimensions,
+            1,
+            capabilities.supported_usage_flags,
+            queue,
+            SurfaceTransform::Identity,
+            alpha,
+            PresentMode::Fifo,
+            FullscreenExclusive::Default,
+            true,
+            ColorSpace::SrgbNonLinear
+        )?;
+
+        let framebuffers = build_framebuffers(device.clone(), render_pass.clone(), dimensions, &images)?;
+
+        Ok(Self {
+            surface,
+            render_pass,
+            swapchain,
+            framebuffers,
+            previous_frame_end: Some(sync::now(device).boxed()),
+            needs_recreate: false
+        })
+    }
+
+    pub fn window(&self) -> &Window {
+        self.surface.window()
+    }
+
+    /// Rebuild the swapchain and its framebuffers against the surface's
+    /// current size. Called from `present` once an acquire/present comes
+    /// back `OutOfDate` (typically after a window resize).
+    fn recreate(&mut self, device: Arc<Device>) -> Result<(), Box<dyn Error>> {
+        let dimensions: [u32; 2] = self.surface.window().inner_size().into();
+
+        let (swapchain, images) = match self.swapchain.recreate_with_dimensions(dimensions) {
+            Ok(result) => result,
+            Err(SwapchainCreationError::UnsupportedDimensions) => return Ok(()),
+            Err(e) => return Err(Box::new(e))
+        };
+
+        self.framebuffers = build_framebuffers(device, self.render_pass.clone(), dimensions, &images)?;
+        self.swapchain = swapchain;
+        self.needs_recreate = false;
+
+        Ok(())
+    }
+
+    /// Acquire the next swapchain image, let `record` draw into its
+    /// framebuffer, then submit and present it - the windowed counterpart to
+    /// the headless copy-to-buffer readback in `Pipeline::render`. Rebuilds
+    /// the swapchain first if a previous frame found it out of date.
+    pub fn present(
+        &mut self,
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        record: impl FnOnce(&mut AutoCommandBufferBuilder, Arc<dyn FramebufferAbstract + Send + Sync>) -> Result<(), Box<dyn Error>>
+    ) -> Result<(), Box<dyn Error>> {
+        self.previous_frame_end.as_mut().unwrap().cleanup_finished();
+
+        if self.needs_recreate {
+            self.recreate(device.clone())?;
+        }
+
+        let (image_index, suboptimal, acquire_future) =
+            match swapchain::acquire_next_image(self.swapchain.clone(), None) {
+                Ok(result) => result,
+                Err(AcquireError::OutOfDate) => {
+                    self.needs_recreate = true;
+                    return Ok(());
+                },
+                Err(e) => return Err(Box::new(e))
+            };
+
+        if suboptimal {
+            self.needs_recreate = true;
+        }
+
+        let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())?;
+        record(&mut builder, self.framebuffers[image_index].clone())?;
+        let command_buffer = builder.build()?;
+
+        let future = self.previous_frame_end
+            .take()
+            .unwrap()
+            .join(acquire_future)
+            .then_execute(queue.clone(), command_buffer)?
+            .then_swapchain_present(queue, self.swapchain.clone(), image_index)
+            .then_signal_fence_and_flush();
+
+        match future {
+            Ok(future) => self.previous_frame_end = Some(future.boxed()),
+            Err(FlushError::OutOfDate) => {
+                self.needs_recreate = true;
+                self.previous_frame_end = Some(sync::now(device).boxed());
+            },
+            Err(e) => return Err(Box::new(e))
+        }
+
+        Ok(())
+    }
+}
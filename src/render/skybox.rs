@@ -0,0 +1,191 @@
+use std::error::Error;
+use std::iter;
+use std::sync::Arc;
+
+use cgmath::Matrix4;
+use image::{ImageBuffer, Rgba};
+
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, CpuBufferPool};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::DynamicState;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::DescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::image::{Dimensions, ImmutableImage};
+use vulkano::pipeline::depth_stencil::{Compare, DepthStencil};
+use vulkano::pipeline::vertex::SingleBufferDefinition;
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::sampler::Sampler;
+
+use super::Vertex;
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/render/skybox_vert.glsl"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/render/skybox_frag.glsl"
+    }
+}
+
+/// Six equal-sized RGBA face images. `into_bytes` concatenates them as
+/// right, left, top, bottom, front, back - the `+X, -X, +Y, -Y, +Z, -Z`
+/// layer order `Dimensions::Cubemap` expects a `samplerCube` to be uploaded
+/// in.
+pub struct SkyboxFaces {
+    pub left: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    pub right: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    pub bottom: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    pub top: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    pub back: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    pub front: ImageBuffer<Rgba<u8>, Vec<u8>>
+}
+
+impl SkyboxFaces {
+    fn size(&self) -> u32 {
+        self.left.width()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        [self.right, self.left, self.top, self.bottom, self.front, self.back]
+            .iter()
+            .flat_map(|face| face.as_raw().iter().cloned())
+            .collect()
+    }
+}
+
+// An inward-facing unit cube: winding is reversed from an outward-facing cube
+// so the faces are visible from the inside, where the camera always sits.
+const CUBE_POSITIONS: [(f32, f32, f32); 8] = [
+    (-1.0, -1.0, -1.0), (1.0, -1.0, -1.0), (1.0, 1.0, -1.0), (-1.0, 1.0, -1.0),
+    (-1.0, -1.0, 1.0), (1.0, -1.0, 1.0), (1.0, 1.0, 1.0), (-1.0, 1.0, 1.0),
+];
+
+const CUBE_INDICES: [u32; 36] = [
+    0, 2, 1, 0, 3, 2,
+    1, 6, 5, 1, 2, 6,
+    5, 7, 4, 5, 6, 7,
+    4, 3, 0, 4, 7, 3,
+    3, 6, 2, 3, 7, 6,
+    4, 1, 5, 4, 0, 1,
+];
+
+/// Background pass: an inward-facing cube sampled with a `samplerCube`,
+/// drawn before the scene with depth writes disabled so geometry draws over
+/// it.
+pub struct Skybox {
+    pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    index_buffer: Arc<CpuAccessibleBuffer<[u32]>>,
+    uniform_buffer: CpuBufferPool<vs::ty::Data>,
+    texture: Arc<ImmutableImage<Format>>,
+    sampler: Arc<Sampler>
+}
+
+impl Skybox {
+    pub fn new(
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+        viewport: Viewport,
+        faces: SkyboxFaces
+    ) -> Result<Self, Box<dyn Error>> {
+        let size = faces.size();
+        let bytes = faces.into_bytes();
+
+        let (texture, init_future) = ImmutableImage::from_iter(
+            bytes.into_iter(),
+            Dimensions::Cubemap { size },
+            Format::R8G8B8A8Unorm,
+            queue.clone()
+        )?;
+        init_future.flush()?;
+
+        let sampler = Sampler::simple_repeat_linear_no_mipmap(device.clone());
+
+        let vs = vs::Shader::load(device.clone())?;
+        let fs = fs::Shader::load(device.clone())?;
+
+        let pipeline = Arc::new(
+            GraphicsPipeline::start()
+                .vertex_input(SingleBufferDefinition::<Vertex>::new())
+                .vertex_shader(vs.main_entry_point(), ())
+                .triangle_list()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .viewports(iter::once(viewport))
+                .fragment_shader(fs.main_entry_point(), ())
+                .depth_stencil(DepthStencil {
+                    depth_write: false,
+                    depth_compare: Compare::LessOrEqual,
+                    ..DepthStencil::simple_depth_test()
+                })
+                .render_pass(Subpass::from(render_pass, 0).unwrap())
+                .build(device.clone())?
+        ) as Arc<dyn GraphicsPipelineAbstract + Send + Sync>;
+
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::all(),
+            false,
+            CUBE_POSITIONS.iter().map(|&position| Vertex { position })
+        )?;
+
+        let index_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::all(),
+            false,
+            CUBE_INDICES.iter().cloned()
+        )?;
+
+        let uniform_buffer = CpuBufferPool::<vs::ty::Data>::new(device, BufferUsage::all());
+
+        Ok(Self { pipeline, vertex_buffer, index_buffer, uniform_buffer, texture, sampler })
+    }
+
+    /// Record the skybox draw into `builder`. Must run inside an active
+    /// render pass, before the scene's own draw calls.
+    pub fn record(
+        &self,
+        builder: &mut AutoCommandBufferBuilder,
+        view: Matrix4<f32>,
+        proj: Matrix4<f32>
+    ) -> Result<(), Box<dyn Error>> {
+        let uniform_data = vs::ty::Data {
+            view: view.into(),
+            proj: proj.into()
+        };
+        let uniform_subbuffer = self.uniform_buffer.next(uniform_data).unwrap();
+
+        let layout = self.pipeline.descriptor_set_layout(0).unwrap();
+        let descriptor_set = Arc::new(
+            PersistentDescriptorSet::start(layout.clone())
+                .add_buffer(uniform_subbuffer)
+                .unwrap()
+                .add_sampled_image(self.texture.clone(), self.sampler.clone())
+                .unwrap()
+                .build()
+                .unwrap()
+        ) as Arc<dyn DescriptorSet + Send + Sync>;
+
+        builder
+            .draw_indexed(
+                self.pipeline.clone(),
+                &DynamicState::none(),
+                vec![self.vertex_buffer.clone()],
+                self.index_buffer.clone(),
+                descriptor_set,
+                ()
+            )
+            .unwrap();
+
+        Ok(())
+    }
+}
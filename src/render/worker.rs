@@ -0,0 +1,114 @@
+use std::error::Error;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use cgmath::Matrix4;
+
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+
+/// Raw per-mesh geometry, already behind `Arc` so it can be handed to a
+/// background worker without copying.
+pub struct MeshJob {
+    pub vertices: Arc<Vec<f32>>,
+    pub normals: Arc<Vec<f32>>,
+    pub indices: Arc<Vec<u32>>,
+    pub transform: Matrix4<f32>
+}
+
+/// GPU buffers a worker produced for one `MeshJob`.
+pub struct MeshUpload {
+    pub vertex_buffer: Arc<CpuAccessibleBuffer<[f32]>>,
+    pub normal_buffer: Arc<CpuAccessibleBuffer<[f32]>>,
+    pub index_buffer: Arc<CpuAccessibleBuffer<[u32]>>,
+    pub transform: Matrix4<f32>
+}
+
+enum Task {
+    Upload(usize, MeshJob, Sender<(usize, Result<MeshUpload, String>)>),
+    Shutdown
+}
+
+/// A small pool of background threads that turn `MeshJob`s into GPU
+/// buffers, so uploading a scene's worth of meshes doesn't serialize on the
+/// main thread the way a plain loop over `CpuAccessibleBuffer::from_iter`
+/// would.
+pub struct BufferWorkers {
+    task_tx: Sender<Task>,
+    handles: Vec<thread::JoinHandle<()>>
+}
+
+impl BufferWorkers {
+    pub fn new(device: Arc<Device>, worker_count: usize) -> Self {
+        let (task_tx, task_rx) = mpsc::channel::<Task>();
+        let task_rx = Arc::new(Mutex::new(task_rx));
+
+        let handles = (0..worker_count.max(1))
+            .map(|_| {
+                let task_rx = task_rx.clone();
+                let device = device.clone();
+
+                thread::spawn(move || loop {
+                    let task = task_rx.lock().unwrap().recv();
+                    match task {
+                        Ok(Task::Upload(index, job, result_tx)) => {
+                            let upload = Self::upload(&device, job).map_err(|e| e.to_string());
+                            let _ = result_tx.send((index, upload));
+                        }
+                        _ => break
+                    }
+                })
+            })
+            .collect();
+
+        Self { task_tx, handles }
+    }
+
+    fn upload(device: &Arc<Device>, job: MeshJob) -> Result<MeshUpload, Box<dyn Error>> {
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(), BufferUsage::all(), false, job.vertices.iter().cloned()
+        )?;
+
+        let normal_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(), BufferUsage::all(), false, job.normals.iter().cloned()
+        )?;
+
+        let index_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(), BufferUsage::all(), false, job.indices.iter().cloned()
+        )?;
+
+        Ok(MeshUpload { vertex_buffer, normal_buffer, index_buffer, transform: job.transform })
+    }
+
+    /// Upload every job across the worker pool and collect the results back
+    /// in submission order.
+    pub fn upload_all(&self, jobs: Vec<MeshJob>) -> Result<Vec<MeshUpload>, Box<dyn Error>> {
+        let (result_tx, result_rx) = mpsc::channel();
+        let job_count = jobs.len();
+
+        for (index, job) in jobs.into_iter().enumerate() {
+            self.task_tx.send(Task::Upload(index, job, result_tx.clone())).unwrap();
+        }
+
+        let mut results: Vec<Option<MeshUpload>> = (0..job_count).map(|_| None).collect();
+        for _ in 0..job_count {
+            let (index, upload) = result_rx.recv().unwrap();
+            results[index] = Some(upload.map_err(|e| -> Box<dyn Error> { e.into() })?);
+        }
+
+        Ok(results.into_iter().map(Option::unwrap).collect())
+    }
+}
+
+impl Drop for BufferWorkers {
+    fn drop(&mut self) {
+        for _ in &self.handles {
+            let _ = self.task_tx.send(Task::Shutdown);
+        }
+
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}